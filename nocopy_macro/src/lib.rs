@@ -12,12 +12,25 @@
 //! Each struct to which `NoCopy` is applied will generate a union type to be used for buffer
 //! operations. Traversal works like this:
 //! * The union can be initialized either as a struct assigned to the `.structure()` field of the
-//! union or using the `MyUnionType::new_buffer()` method and providing a slice
-//! * The union also provides methods `.get_field_name` and `.set_field_name` that are
-//! generated per struct field
-//! * Getters and setters will respect endianness specified by the attribute `#[nocopy_macro(endian =
-//! "big")]` or `#[nocopy_macro(endian = "little")]`
-//! provided in the original struct
+//! union, using the `MyUnionType::new_buffer()` method and providing a fixed-size `[u8; N]` array,
+//! or using `MyUnionType::try_from_slice()` and providing a runtime byte slice (e.g. bytes read
+//! from a socket or file), which fails with a generated `MyUnionTypeSizeError` if the slice is the
+//! wrong length, or using `MyUnionType::from_fields()` with one argument per struct field, which
+//! writes each value through its generated setter so the buffer is built endianness-correct
+//! * The union also provides methods `.get_field_name` and `.set_field_name` that are generated per
+//! struct field
+//! * Getters and setters will respect endianness specified by the attribute `#[nocopy_macro(endian
+//! = "big")]` or `#[nocopy_macro(endian = "little")]` provided in the original struct.
+//! Endianness-aware accessors are generated for every integer width (`u8`/`i8` through
+//! `u128`/`i128`) as well as `f32`/`f64`, the latter by byte-swapping their bit representation.
+//! Fixed-size arrays (`[T; N]`) are also supported: single-byte element arrays (`u8`/`i8`) always
+//! get direct `&[T; N]`/`&mut [T; N]` accessors, since byte order is meaningless for one-byte
+//! elements; arrays of a wider integer type get the same direct accessors when no endian attribute
+//! applies, or a getter-by-value/setter pair that byte-swaps each element when one does (with no
+//! `_mut` accessor, since a `&mut` into the backing storage holds wire-order bytes and can't
+//! transparently swap on every write); arrays of any other element type reject an endian attribute
+//! with a compile error since there is no defined byte-swap for them. Any other field type falls
+//! back to native-order access.
 //!
 //! # Recognized attributes
 //! Attributes can be added to the struct to specify whether integer types should be interpreted
@@ -25,6 +38,10 @@
 //! or `#[nocopy_macro(endian = "little")]`. If neither is specified, native endian is assumed for
 //! integers. Another available attribute is provided as `#[nocopy_macro(name = "MyUnionNameHere")]`
 //! to override the default name for the autogenerated union.
+//!
+//! The same `#[nocopy_macro(endian = "...")]` attribute can also be placed on individual fields
+//! (plus `"native"` is accepted there) to override the struct-level endianness for that field
+//! alone, which is handy for formats that mix byte orders within a single header.
 
 extern crate proc_macro;
 extern crate quote;
@@ -33,8 +50,8 @@ extern crate syn;
 use proc_macro::TokenStream;
 use quote::quote;
 use syn::{
-    export::Span, DeriveInput, Field, Ident, Lit, Meta, MetaList, MetaNameValue, NestedMeta, Path,
-    Type,
+    export::Span, spanned::Spanned, DeriveInput, Field, Ident, Lit, Meta, MetaList, MetaNameValue,
+    NestedMeta, Path, Type,
 };
 
 enum Endian {
@@ -43,14 +60,34 @@ enum Endian {
     Default,
 }
 
-fn extract_meta(ast: &syn::DeriveInput) -> (Ident, Endian) {
+/// Accumulates `syn::Error`s discovered while expanding the derive instead of aborting on the
+/// first one, so a struct with several problems gets them reported together.
+#[derive(Default)]
+struct Diagnostics(Vec<syn::Error>);
+
+impl Diagnostics {
+    fn push(&mut self, span: Span, message: impl std::fmt::Display) {
+        self.0.push(syn::Error::new(span, message));
+    }
+
+    fn into_compile_error(self) -> Option<quote::__rt::TokenStream> {
+        let mut errors = self.0.into_iter();
+        let mut combined = errors.next()?;
+        for err in errors {
+            combined.combine(err);
+        }
+        Some(combined.to_compile_error())
+    }
+}
+
+fn extract_meta(ast: &syn::DeriveInput, errors: &mut Diagnostics) -> (Ident, Endian) {
     let mut endian = Endian::Default;
     let mut ident = None;
     for attr in &ast.attrs {
-        match attr.style {
-            syn::AttrStyle::Outer => (),
-            _ => panic!("Only outer attributes allowed here"),
-        };
+        if let syn::AttrStyle::Inner(_) = attr.style {
+            errors.push(attr.span(), "Only outer attributes allowed here");
+            continue;
+        }
         let ncp_path = &attr.path;
         if ncp_path.get_ident() != Some(&Ident::new("nocopy_macro", Span::call_site())) {
             continue;
@@ -70,7 +107,10 @@ fn extract_meta(ast: &syn::DeriveInput) -> (Ident, Endian) {
                             eq_token: _,
                             lit: Lit::Str(s),
                         })) => (path, s),
-                        _ => panic!("Malformed macro attribute"),
+                        other => {
+                            errors.push(other.span(), "Malformed macro attribute");
+                            continue;
+                        }
                     };
                     let name_path = syn::parse::<Path>(TokenStream::from(quote! {
                         name
@@ -89,12 +129,18 @@ fn extract_meta(ast: &syn::DeriveInput) -> (Ident, Endian) {
                         endian = match s.value().as_str() {
                             "big" => Endian::Big,
                             "little" => Endian::Little,
-                            _ => panic!("Unrecognized \"endian\" option"),
+                            _ => {
+                                errors.push(s.span(), "Unrecognized \"endian\" option");
+                                Endian::Default
+                            }
                         }
                     }
                 }
             }
-            _ => panic!("Outer attribute must be in the form #[nocopy_macro(key = \"value\")]"),
+            _ => errors.push(
+                attr.span(),
+                "Outer attribute must be in the form #[nocopy_macro(key = \"value\")]",
+            ),
         };
     }
     (
@@ -106,6 +152,60 @@ fn extract_meta(ast: &syn::DeriveInput) -> (Ident, Endian) {
     )
 }
 
+fn extract_field_endian(field: &Field, errors: &mut Diagnostics) -> Option<Endian> {
+    let mut endian = None;
+    for attr in &field.attrs {
+        let ncp_path = &attr.path;
+        if ncp_path.get_ident() != Some(&Ident::new("nocopy_macro", Span::call_site())) {
+            continue;
+        }
+        let attrnamemeta = attr.parse_meta();
+
+        match attrnamemeta {
+            Ok(Meta::List(MetaList {
+                path: _,
+                paren_token: _,
+                nested,
+            })) => {
+                for nest in nested.into_iter() {
+                    let (path, s) = match nest {
+                        NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                            path,
+                            eq_token: _,
+                            lit: Lit::Str(s),
+                        })) => (path, s),
+                        other => {
+                            errors.push(other.span(), "Malformed macro attribute");
+                            continue;
+                        }
+                    };
+                    let endian_path = syn::parse::<Path>(TokenStream::from(quote! {
+                        endian
+                    }))
+                    .expect("Should be a valid path");
+
+                    if path == endian_path {
+                        endian = match s.value().as_str() {
+                            "big" => Some(Endian::Big),
+                            "little" => Some(Endian::Little),
+                            "native" => Some(Endian::Default),
+                            _ => {
+                                errors.push(s.span(), "Unrecognized \"endian\" option");
+                                None
+                            }
+                        }
+                    }
+                }
+            }
+            _ => errors.push(
+                attr.span(),
+                "Outer attribute must be in the form #[nocopy_macro(key = \"value\")]",
+            ),
+        };
+    }
+    endian
+}
+
 fn big_endian(
     ident: &Ident,
     get_ident: &Ident,
@@ -157,101 +257,310 @@ fn native_endian(
     }
 }
 
-fn match_endian(named_field: &Field, endian: &Endian) -> quote::__rt::TokenStream {
-    let ident = match named_field.ident {
-        Some(ref idt) => idt,
-        None => panic!("All struct fields must be named"),
+fn big_endian_float(
+    ident: &Ident,
+    get_ident: &Ident,
+    set_ident: &Ident,
+    ty: &Type,
+    bits_ty: &Type,
+) -> quote::__rt::TokenStream {
+    quote! {
+        pub fn #get_ident(&self) -> #ty {
+            unsafe { #ty::from_bits(#bits_ty::from_be(self.structure.#ident.to_bits())) }
+        }
+
+        pub fn #set_ident(&mut self, v: #ty) {
+            unsafe { self.structure.#ident = #ty::from_bits(v.to_bits().to_be()); }
+        }
+    }
+}
+
+fn little_endian_float(
+    ident: &Ident,
+    get_ident: &Ident,
+    set_ident: &Ident,
+    ty: &Type,
+    bits_ty: &Type,
+) -> quote::__rt::TokenStream {
+    quote! {
+        pub fn #get_ident(&self) -> #ty {
+            unsafe { #ty::from_bits(#bits_ty::from_le(self.structure.#ident.to_bits())) }
+        }
+
+        pub fn #set_ident(&mut self, v: #ty) {
+            unsafe { self.structure.#ident = #ty::from_bits(v.to_bits().to_le()); }
+        }
+    }
+}
+
+fn parse_ty(tokens: quote::__rt::TokenStream) -> Type {
+    syn::parse::<Type>(TokenStream::from(tokens)).expect("Should be a valid type")
+}
+
+/// No byte order to respect (either the element type is `u8`/`i8` or no endian attribute applies), so
+/// getters hand out references straight into the union's backing storage.
+fn array_passthrough(
+    ident: &Ident,
+    get_ident: &Ident,
+    get_mut_ident: &Ident,
+    set_ident: &Ident,
+    ty: &Type,
+) -> quote::__rt::TokenStream {
+    quote! {
+        pub fn #get_ident(&self) -> &#ty {
+            unsafe { &self.structure.#ident }
+        }
+
+        pub fn #get_mut_ident(&mut self) -> &mut #ty {
+            unsafe { &mut self.structure.#ident }
+        }
+
+        pub fn #set_ident(&mut self, v: #ty) {
+            unsafe { self.structure.#ident = v; }
+        }
+    }
+}
+
+fn big_endian_array(
+    ident: &Ident,
+    get_ident: &Ident,
+    set_ident: &Ident,
+    ty: &Type,
+    elem_ty: &Type,
+) -> quote::__rt::TokenStream {
+    quote! {
+        /// Returns a byte-swapped copy of the array. There is no `_mut` accessor for
+        /// endian-swapped arrays: a `&mut` into the backing storage holds wire-order bytes, so it
+        /// cannot transparently swap on every write the way the setter does.
+        pub fn #get_ident(&self) -> #ty {
+            let mut out = unsafe { self.structure.#ident };
+            for item in out.iter_mut() {
+                *item = #elem_ty::from_be(*item);
+            }
+            out
+        }
+
+        pub fn #set_ident(&mut self, v: #ty) {
+            let mut swapped = v;
+            for item in swapped.iter_mut() {
+                *item = item.to_be();
+            }
+            unsafe { self.structure.#ident = swapped; }
+        }
+    }
+}
+
+fn little_endian_array(
+    ident: &Ident,
+    get_ident: &Ident,
+    set_ident: &Ident,
+    ty: &Type,
+    elem_ty: &Type,
+) -> quote::__rt::TokenStream {
+    quote! {
+        /// Returns a byte-swapped copy of the array. There is no `_mut` accessor for
+        /// endian-swapped arrays: a `&mut` into the backing storage holds wire-order bytes, so it
+        /// cannot transparently swap on every write the way the setter does.
+        pub fn #get_ident(&self) -> #ty {
+            let mut out = unsafe { self.structure.#ident };
+            for item in out.iter_mut() {
+                *item = #elem_ty::from_le(*item);
+            }
+            out
+        }
+
+        pub fn #set_ident(&mut self, v: #ty) {
+            let mut swapped = v;
+            for item in swapped.iter_mut() {
+                *item = item.to_le();
+            }
+            unsafe { self.structure.#ident = swapped; }
+        }
+    }
+}
+
+fn match_endian(
+    named_field: &Field,
+    endian: &Endian,
+    errors: &mut Diagnostics,
+) -> Option<quote::__rt::TokenStream> {
+    let ident = match &named_field.ident {
+        Some(idt) => idt,
+        None => {
+            errors.push(named_field.span(), "All struct fields must be named");
+            return None;
+        }
     };
-    let get_ident = Ident::new(
-        format!(
-            "get_{}",
-            named_field
-                .ident
-                .as_ref()
-                .expect("All fields must be named")
-        )
-        .as_str(),
-        Span::call_site(),
-    );
-    let set_ident = Ident::new(
-        format!(
-            "set_{}",
-            named_field
-                .ident
-                .as_ref()
-                .expect("All fields must be named")
-        )
-        .as_str(),
-        Span::call_site(),
-    );
+    let get_ident = Ident::new(format!("get_{}", ident).as_str(), Span::call_site());
+    let set_ident = Ident::new(format!("set_{}", ident).as_str(), Span::call_site());
     let ty = &named_field.ty;
 
-    let u8_ty = syn::parse::<Type>(TokenStream::from(quote! {
-        u8
-    }))
-    .expect("Should be a valid type");
-    let u16_ty = syn::parse::<Type>(TokenStream::from(quote! {
-        u16
-    }))
-    .expect("Should be a valid type");
-    let u32_ty = syn::parse::<Type>(TokenStream::from(quote! {
-        u32
-    }))
-    .expect("Should be a valid type");
-    let u64_ty = syn::parse::<Type>(TokenStream::from(quote! {
-        u64
-    }))
-    .expect("Should be a valid type");
-
-    if *ty == u8_ty || *ty == u16_ty || *ty == u32_ty || *ty == u64_ty {
-        match endian {
-            Endian::Big => big_endian(&ident, &get_ident, &set_ident, ty),
-            Endian::Little => little_endian(&ident, &get_ident, &set_ident, ty),
-            Endian::Default => native_endian(&ident, &get_ident, &set_ident, ty),
+    let int_tys = [
+        parse_ty(quote! { u8 }),
+        parse_ty(quote! { u16 }),
+        parse_ty(quote! { u32 }),
+        parse_ty(quote! { u64 }),
+        parse_ty(quote! { u128 }),
+        parse_ty(quote! { i8 }),
+        parse_ty(quote! { i16 }),
+        parse_ty(quote! { i32 }),
+        parse_ty(quote! { i64 }),
+        parse_ty(quote! { i128 }),
+    ];
+    let f32_ty = parse_ty(quote! { f32 });
+    let f64_ty = parse_ty(quote! { f64 });
+
+    let field_endian = extract_field_endian(named_field, errors);
+    let endian = field_endian.as_ref().unwrap_or(endian);
+
+    if int_tys.iter().any(|int_ty| int_ty == ty) {
+        return Some(match endian {
+            Endian::Big => big_endian(ident, &get_ident, &set_ident, ty),
+            Endian::Little => little_endian(ident, &get_ident, &set_ident, ty),
+            Endian::Default => native_endian(ident, &get_ident, &set_ident, ty),
+        });
+    }
+
+    if *ty == f32_ty {
+        let bits_ty = parse_ty(quote! { u32 });
+        return Some(match endian {
+            Endian::Big => big_endian_float(ident, &get_ident, &set_ident, ty, &bits_ty),
+            Endian::Little => little_endian_float(ident, &get_ident, &set_ident, ty, &bits_ty),
+            Endian::Default => native_endian(ident, &get_ident, &set_ident, ty),
+        });
+    }
+
+    if *ty == f64_ty {
+        let bits_ty = parse_ty(quote! { u64 });
+        return Some(match endian {
+            Endian::Big => big_endian_float(ident, &get_ident, &set_ident, ty, &bits_ty),
+            Endian::Little => little_endian_float(ident, &get_ident, &set_ident, ty, &bits_ty),
+            Endian::Default => native_endian(ident, &get_ident, &set_ident, ty),
+        });
+    }
+
+    if let Type::Array(array_ty) = ty {
+        let elem_ty = &*array_ty.elem;
+        let get_mut_ident = Ident::new(format!("get_{}_mut", ident).as_str(), Span::call_site());
+
+        // u8/i8 elements are a single byte, so byte order is meaningless for them: they always
+        // get the direct reference accessors, regardless of any endian attribute.
+        let is_single_byte = *elem_ty == int_tys[0] || *elem_ty == int_tys[5];
+        let is_swappable_int = !is_single_byte && int_tys.iter().any(|int_ty| int_ty == elem_ty);
+
+        if is_single_byte {
+            return Some(array_passthrough(
+                ident,
+                &get_ident,
+                &get_mut_ident,
+                &set_ident,
+                ty,
+            ));
         }
-    } else {
-        native_endian(&ident, &get_ident, &set_ident, ty)
+
+        if is_swappable_int {
+            return Some(match endian {
+                Endian::Big => big_endian_array(ident, &get_ident, &set_ident, ty, elem_ty),
+                Endian::Little => little_endian_array(ident, &get_ident, &set_ident, ty, elem_ty),
+                Endian::Default => {
+                    array_passthrough(ident, &get_ident, &get_mut_ident, &set_ident, ty)
+                }
+            });
+        }
+
+        // No byte-swap is defined for non-integer element types (e.g. `f32`/`f64`), so an
+        // explicit endian attribute on them is a compile error rather than a silent no-op.
+        return match endian {
+            Endian::Default => Some(array_passthrough(
+                ident,
+                &get_ident,
+                &get_mut_ident,
+                &set_ident,
+                ty,
+            )),
+            Endian::Big | Endian::Little => {
+                errors.push(
+                    array_ty.elem.span(),
+                    "endian-swapped arrays are only supported for integer element types",
+                );
+                None
+            }
+        };
     }
+
+    Some(native_endian(ident, &get_ident, &set_ident, ty))
 }
 
 /// Procedural macro that will derive getters and setters with appropriate endianness for every
 /// field defined in the struct
 #[proc_macro_derive(NoCopy, attributes(nocopy_macro))]
 pub fn no_copy(input: TokenStream) -> TokenStream {
-    let ast: DeriveInput = syn::parse(input).expect("Failed to parse input");
-
-    if ast
-        .attrs
-        .iter()
-        .filter(|item| {
-            item.parse_meta().expect("Provided attribute not valid")
-                == syn::parse::<Meta>(TokenStream::from(quote! {
-                    repr(C)
-                }))
-                .expect("Should be a valid attribute")
+    let ast: DeriveInput = match syn::parse(input) {
+        Ok(ast) => ast,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    let mut errors = Diagnostics::default();
+
+    let has_repr_c = ast.attrs.iter().any(|item| {
+        item.parse_meta().is_ok_and(|meta| {
+            meta == syn::parse::<Meta>(TokenStream::from(quote! {
+                repr(C)
+            }))
+            .expect("Should be a valid attribute")
         })
-        .collect::<Vec<_>>()
-        .len()
-        < 1
-    {
-        panic!("Struct must be marked as #[repr(C)] to be used with this derive")
+    });
+    if !has_repr_c {
+        errors.push(
+            ast.ident.span(),
+            "Struct must be marked as #[repr(C)] to be used with this derive",
+        );
     }
 
     let name = &ast.ident;
-    let (attrname, endian) = extract_meta(&ast);
+    let (attrname, endian) = extract_meta(&ast, &mut errors);
+    let error_name = Ident::new(format!("{}SizeError", attrname).as_str(), Span::call_site());
+    let error_doc = format!(
+        "Error returned by `{}::try_from_slice` when the provided slice does not match the \
+         expected buffer size for `{}`.",
+        attrname, name
+    );
 
-    let fields = match ast.data {
-        syn::Data::Struct(structure) => structure.fields,
-        _ => panic!("This macro only supports structs"),
-    };
-    let field_pairs = match fields {
-        syn::Fields::Named(named) => named.named,
-        _ => panic!("This macro only supports structs with named fields"),
+    let field_pairs = match ast.data {
+        syn::Data::Struct(ref structure) => match &structure.fields {
+            syn::Fields::Named(named) => Some(named.named.clone()),
+            other => {
+                errors.push(
+                    other.span(),
+                    "This macro only supports structs with named fields",
+                );
+                None
+            }
+        },
+        _ => {
+            errors.push(ast.ident.span(), "This macro only supports structs");
+            None
+        }
     };
 
     let mut funcs_vec = Vec::new();
-    for named_field in field_pairs {
-        funcs_vec.push(match_endian(&named_field, &endian));
+    let mut field_idents = Vec::new();
+    let mut field_tys = Vec::new();
+    let mut set_idents = Vec::new();
+    if let Some(field_pairs) = field_pairs {
+        for named_field in field_pairs {
+            if let Some(tokens) = match_endian(&named_field, &endian, &mut errors) {
+                funcs_vec.push(tokens);
+                let ident = named_field.ident.clone().expect("checked by match_endian");
+                set_idents.push(Ident::new(format!("set_{}", ident).as_str(), Span::call_site()));
+                field_idents.push(ident);
+                field_tys.push(named_field.ty.clone());
+            }
+        }
+    }
+
+    if let Some(compile_error) = errors.into_compile_error() {
+        return compile_error.into();
     }
 
     TokenStream::from(quote! {
@@ -267,13 +576,59 @@ pub fn no_copy(input: TokenStream) -> TokenStream {
                 #attrname { buffer }
             }
 
+            pub fn try_from_slice(bytes: &[u8]) -> Result<Self, #error_name> {
+                let expected = std::mem::size_of::<#name>();
+                if bytes.len() != expected {
+                    return Err(#error_name {
+                        expected,
+                        found: bytes.len(),
+                    });
+                }
+                let mut buffer = [0u8; std::mem::size_of::<#name>()];
+                buffer.copy_from_slice(bytes);
+                Ok(#attrname { buffer })
+            }
+
             pub fn as_buffer(&self) -> &[u8] {
                 unsafe { &self.buffer }
             }
 
+            pub fn as_buffer_mut(&mut self) -> &mut [u8] {
+                unsafe { &mut self.buffer }
+            }
+
+            pub fn from_fields(#(#field_idents: #field_tys),*) -> Self {
+                let mut instance = #attrname {
+                    buffer: [0u8; std::mem::size_of::<#name>()],
+                };
+                #(
+                    instance.#set_idents(#field_idents);
+                )*
+                instance
+            }
+
             #(
                 #funcs_vec
             )*
         }
+
+        #[doc = #error_doc]
+        #[derive(Debug)]
+        pub struct #error_name {
+            pub expected: usize,
+            pub found: usize,
+        }
+
+        impl std::fmt::Display for #error_name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(
+                    f,
+                    "expected a buffer of {} bytes, found {} bytes",
+                    self.expected, self.found
+                )
+            }
+        }
+
+        impl std::error::Error for #error_name {}
     })
 }